@@ -1,8 +1,59 @@
 use super::plumbing::*;
 use super::*;
 
+use std::cmp;
 use std::fmt::{self, Debug};
 
+/// A splitting policy attached to a sub-graph by [`with_min_len()`]/[`with_max_len()`].
+///
+/// A `Setup` only ever tightens the base producer's splitting: it raises the
+/// effective `min_len` (never below the base producer's real `min_len`) and
+/// caps the effective `max_len`. Both fields are optional, so a default `Setup`
+/// defers entirely to the base and behaves exactly as if no policy were
+/// attached.
+///
+/// [`with_min_len()`]: trait.ParallelIteratorExt.html#method.with_min_len
+/// [`with_max_len()`]: trait.ParallelIteratorExt.html#method.with_max_len
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Setup {
+    pub min_len: Option<usize>,
+    pub max_len: Option<usize>,
+}
+
+impl Setup {
+    /// Merge two setups, keeping the more restrictive bound from each: the
+    /// larger `min_len` and the tighter (smaller) `max_len`.
+    pub fn merge(self, other: Setup) -> Setup {
+        Setup {
+            min_len: match (self.min_len, other.min_len) {
+                (Some(a), Some(b)) => Some(cmp::max(a, b)),
+                (a, b) => a.or(b),
+            },
+            max_len: match (self.max_len, other.max_len) {
+                (Some(a), Some(b)) => Some(cmp::min(a, b)),
+                (a, b) => a.or(b),
+            },
+        }
+    }
+
+    /// Effective `min_len`, raised to the policy's `min_len` but never below the
+    /// base producer's real minimum.
+    fn min_len(&self, base: usize) -> usize {
+        match self.min_len {
+            Some(min_len) => cmp::max(min_len, base),
+            None => base,
+        }
+    }
+
+    /// Effective `max_len`, capped at the policy's `max_len` when one is set.
+    fn max_len(&self, base: usize) -> usize {
+        match self.max_len {
+            Some(max_len) => cmp::min(max_len, base),
+            None => base,
+        }
+    }
+}
+
 
 /// `MapWith` is an iterator that transforms the elements of an underlying iterator.
 ///
@@ -548,3 +599,579 @@ impl<'f, T, INIT, U, R, C, F> UnindexedConsumer<T> for MapInitConsumer<'f, C, IN
         self.base.to_reducer()
     }
 }
+
+// ------------------------------------------------------------------------------------------------
+
+/// `Update` is an iterator that mutates the elements of an underlying iterator
+/// before they are yielded.
+///
+/// This struct is created by the [`update()`] method on [`ParallelIteratorExt`]
+///
+/// [`update()`]: trait.ParallelIteratorExt.html#method.update
+/// [`ParallelIteratorExt`]: trait.ParallelIteratorExt.html
+#[must_use = "iterator adaptors are lazy and do nothing unless consumed"]
+#[derive(Clone)]
+pub struct Update<I: ParallelIterator, F> {
+    base: I,
+    update_op: F,
+}
+
+impl<I: ParallelIterator + Debug, F> Debug for Update<I, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Update")
+            .field("base", &self.base)
+            .finish()
+    }
+}
+
+/// Create a new `Update` iterator.
+///
+/// NB: a free fn because it is NOT part of the end-user API.
+pub fn new_update<I, F>(base: I, update_op: F) -> Update<I, F>
+    where I: ParallelIterator
+{
+    Update {
+        base: base,
+        update_op: update_op,
+    }
+}
+
+impl<I, F> ParallelIterator for Update<I, F>
+    where I: ParallelIterator,
+          F: Fn(&mut I::Item) + Sync + Send
+{
+    type Item = I::Item;
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+        where C: UnindexedConsumer<Self::Item>
+    {
+        let consumer1 = UpdateConsumer::new(consumer, &self.update_op);
+        self.base.drive_unindexed(consumer1)
+    }
+
+    fn opt_len(&self) -> Option<usize> {
+        self.base.opt_len()
+    }
+}
+
+impl<I, F> IndexedParallelIterator for Update<I, F>
+    where I: IndexedParallelIterator,
+          F: Fn(&mut I::Item) + Sync + Send
+{
+    fn drive<C>(self, consumer: C) -> C::Result
+        where C: Consumer<Self::Item>
+    {
+        let consumer1 = UpdateConsumer::new(consumer, &self.update_op);
+        self.base.drive(consumer1)
+    }
+
+    fn len(&self) -> usize {
+        self.base.len()
+    }
+
+    fn with_producer<CB>(self, callback: CB) -> CB::Output
+        where CB: ProducerCallback<Self::Item>
+    {
+        return self.base.with_producer(Callback {
+                                           callback: callback,
+                                           update_op: self.update_op,
+                                       });
+
+        struct Callback<CB, F> {
+            callback: CB,
+            update_op: F,
+        }
+
+        impl<T, F, CB> ProducerCallback<T> for Callback<CB, F>
+            where CB: ProducerCallback<T>,
+                  F: Fn(&mut T) + Sync
+        {
+            type Output = CB::Output;
+
+            fn callback<P>(self, base: P) -> CB::Output
+                where P: Producer<Item = T>
+            {
+                let producer = UpdateProducer {
+                    base: base,
+                    update_op: &self.update_op,
+                };
+                self.callback.callback(producer)
+            }
+        }
+    }
+}
+
+/// ////////////////////////////////////////////////////////////////////////
+
+struct UpdateProducer<'f, P, F: 'f> {
+    base: P,
+    update_op: &'f F,
+}
+
+impl<'f, P, F> Producer for UpdateProducer<'f, P, F>
+    where P: Producer,
+          F: Fn(&mut P::Item) + Sync
+{
+    type Item = P::Item;
+    type IntoIter = UpdateIter<'f, P::IntoIter, F>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        UpdateIter {
+            base: self.base.into_iter(),
+            update_op: self.update_op,
+        }
+    }
+
+    fn min_len(&self) -> usize {
+        self.base.min_len()
+    }
+    fn max_len(&self) -> usize {
+        self.base.max_len()
+    }
+
+    fn split_at(self, index: usize) -> (Self, Self) {
+        let (left, right) = self.base.split_at(index);
+        (UpdateProducer {
+             base: left,
+             update_op: self.update_op,
+         },
+         UpdateProducer {
+             base: right,
+             update_op: self.update_op,
+         })
+    }
+
+    fn fold_with<G>(self, folder: G) -> G
+        where G: Folder<Self::Item>
+    {
+        let folder1 = UpdateFolder {
+            base: folder,
+            update_op: self.update_op,
+        };
+        self.base.fold_with(folder1).base
+    }
+}
+
+struct UpdateIter<'f, I, F: 'f> {
+    base: I,
+    update_op: &'f F,
+}
+
+impl<'f, I, F> Iterator for UpdateIter<'f, I, F>
+    where I: Iterator,
+          F: Fn(&mut I::Item)
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.base.next().map(|mut item| {
+            (self.update_op)(&mut item);
+            item
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.base.size_hint()
+    }
+}
+
+impl<'f, I, F> DoubleEndedIterator for UpdateIter<'f, I, F>
+    where I: DoubleEndedIterator,
+          F: Fn(&mut I::Item)
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.base.next_back().map(|mut item| {
+            (self.update_op)(&mut item);
+            item
+        })
+    }
+}
+
+impl<'f, I, F> ExactSizeIterator for UpdateIter<'f, I, F>
+    where I: ExactSizeIterator,
+          F: Fn(&mut I::Item)
+{
+}
+
+
+/// ////////////////////////////////////////////////////////////////////////
+/// Consumer implementation
+
+struct UpdateConsumer<'f, C, F: 'f> {
+    base: C,
+    update_op: &'f F,
+}
+
+impl<'f, C, F> UpdateConsumer<'f, C, F> {
+    fn new(base: C, update_op: &'f F) -> Self {
+        UpdateConsumer {
+            base: base,
+            update_op: update_op,
+        }
+    }
+}
+
+impl<'f, T, C, F> Consumer<T> for UpdateConsumer<'f, C, F>
+    where C: Consumer<T>,
+          F: Fn(&mut T) + Sync
+{
+    type Folder = UpdateFolder<'f, C::Folder, F>;
+    type Reducer = C::Reducer;
+    type Result = C::Result;
+
+    fn split_at(self, index: usize) -> (Self, Self, Self::Reducer) {
+        let (left, right, reducer) = self.base.split_at(index);
+        (UpdateConsumer::new(left, self.update_op),
+         UpdateConsumer::new(right, self.update_op),
+         reducer)
+    }
+
+    fn into_folder(self) -> Self::Folder {
+        UpdateFolder {
+            base: self.base.into_folder(),
+            update_op: self.update_op,
+        }
+    }
+
+    fn full(&self) -> bool {
+        self.base.full()
+    }
+}
+
+impl<'f, T, C, F> UnindexedConsumer<T> for UpdateConsumer<'f, C, F>
+    where C: UnindexedConsumer<T>,
+          F: Fn(&mut T) + Sync
+{
+    fn split_off_left(&self) -> Self {
+        UpdateConsumer::new(self.base.split_off_left(), self.update_op)
+    }
+
+    fn to_reducer(&self) -> Self::Reducer {
+        self.base.to_reducer()
+    }
+}
+
+struct UpdateFolder<'f, C, F: 'f> {
+    base: C,
+    update_op: &'f F,
+}
+
+impl<'f, T, C, F> Folder<T> for UpdateFolder<'f, C, F>
+    where C: Folder<T>,
+          F: Fn(&mut T)
+{
+    type Result = C::Result;
+
+    fn consume(mut self, mut item: T) -> Self {
+        (self.update_op)(&mut item);
+        self.base = self.base.consume(item);
+        self
+    }
+
+    fn consume_iter<I>(mut self, iter: I) -> Self
+        where I: IntoIterator<Item = T>
+    {
+        {
+            let update_op = self.update_op;
+            let mapped_iter = iter.into_iter().map(|mut x| {
+                update_op(&mut x);
+                x
+            });
+            self.base = self.base.consume_iter(mapped_iter);
+        }
+        self
+    }
+
+    fn complete(self) -> C::Result {
+        self.base.complete()
+    }
+
+    fn full(&self) -> bool {
+        self.base.full()
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+/// Extension methods on [`ParallelIterator`] for the adaptors defined in this
+/// module.
+///
+/// These live on a blanket-implemented sub-trait, so `update()` is available on
+/// every [`ParallelIterator`]; `with_min_len()` and `with_max_len()` are
+/// available only where the iterator is also an `IndexedParallelIterator`.
+///
+/// [`ParallelIterator`]: trait.ParallelIterator.html
+pub trait ParallelIteratorExt: ParallelIterator {
+    /// Mutates each item of this iterator in place before yielding it.
+    ///
+    /// Unlike [`map()`], `update` hands each item to `update_op` by `&mut` and
+    /// yields the (same-typed) item back, so chains stay ergonomic when the
+    /// closure has nothing to return:
+    ///
+    /// ```ignore
+    /// vec.into_par_iter().update(|s| s.push_str("!"))
+    /// ```
+    ///
+    /// [`map()`]: trait.ParallelIterator.html#method.map
+    fn update<OP>(self, update_op: OP) -> Update<Self, OP>
+        where OP: Fn(&mut Self::Item) + Sync + Send,
+              Self: Sized
+    {
+        new_update(self, update_op)
+    }
+
+    /// Attaches a minimum chunk length to this sub-graph, forcing coarser
+    /// splitting locally without affecting the rest of the pipeline. The value
+    /// only ever raises the effective `min_len`; it never drops below the base
+    /// producer's own minimum.
+    fn with_min_len(self, min_len: usize) -> WithSetup<Self>
+        where Self: IndexedParallelIterator + Sized
+    {
+        new_with_setup(self, Setup { min_len: Some(min_len), ..Setup::default() })
+    }
+
+    /// Attaches a maximum chunk length to this sub-graph, capping the effective
+    /// `max_len` locally without affecting the rest of the pipeline.
+    fn with_max_len(self, max_len: usize) -> WithSetup<Self>
+        where Self: IndexedParallelIterator + Sized
+    {
+        new_with_setup(self, Setup { max_len: Some(max_len), ..Setup::default() })
+    }
+}
+
+impl<I: ParallelIterator> ParallelIteratorExt for I {}
+
+// ------------------------------------------------------------------------------------------------
+
+/// `WithSetup` attaches a [`Setup`] splitting policy to an underlying iterator.
+///
+/// This struct is created by the [`with_min_len()`]/[`with_max_len()`] methods
+/// on [`ParallelIteratorExt`].
+///
+/// [`Setup`]: struct.Setup.html
+/// [`with_min_len()`]: trait.ParallelIteratorExt.html#method.with_min_len
+/// [`with_max_len()`]: trait.ParallelIteratorExt.html#method.with_max_len
+/// [`ParallelIteratorExt`]: trait.ParallelIteratorExt.html
+#[must_use = "iterator adaptors are lazy and do nothing unless consumed"]
+#[derive(Clone)]
+pub struct WithSetup<I> {
+    base: I,
+    setup: Setup,
+}
+
+impl<I: ParallelIterator + Debug> Debug for WithSetup<I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WithSetup")
+            .field("base", &self.base)
+            .field("setup", &self.setup)
+            .finish()
+    }
+}
+
+/// Create a new `WithSetup` iterator.
+///
+/// NB: a free fn because it is NOT part of the end-user API.
+pub fn new_with_setup<I>(base: I, setup: Setup) -> WithSetup<I>
+    where I: IndexedParallelIterator
+{
+    WithSetup {
+        base: base,
+        setup: setup,
+    }
+}
+
+impl<I> WithSetup<I> {
+    /// Tighten the attached minimum chunk length, merging with any policy this
+    /// adaptor already carries.
+    pub fn with_min_len(self, min_len: usize) -> WithSetup<I> {
+        WithSetup {
+            setup: self.setup.merge(Setup { min_len: Some(min_len), ..Setup::default() }),
+            base: self.base,
+        }
+    }
+
+    /// Tighten the attached maximum chunk length, merging with any policy this
+    /// adaptor already carries.
+    pub fn with_max_len(self, max_len: usize) -> WithSetup<I> {
+        WithSetup {
+            setup: self.setup.merge(Setup { max_len: Some(max_len), ..Setup::default() }),
+            base: self.base,
+        }
+    }
+}
+
+impl<I> ParallelIterator for WithSetup<I>
+    where I: IndexedParallelIterator
+{
+    type Item = I::Item;
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+        where C: UnindexedConsumer<Self::Item>
+    {
+        bridge(self, consumer)
+    }
+
+    fn opt_len(&self) -> Option<usize> {
+        self.base.opt_len()
+    }
+}
+
+impl<I> IndexedParallelIterator for WithSetup<I>
+    where I: IndexedParallelIterator
+{
+    fn drive<C>(self, consumer: C) -> C::Result
+        where C: Consumer<Self::Item>
+    {
+        bridge(self, consumer)
+    }
+
+    fn len(&self) -> usize {
+        self.base.len()
+    }
+
+    fn with_producer<CB>(self, callback: CB) -> CB::Output
+        where CB: ProducerCallback<Self::Item>
+    {
+        return self.base.with_producer(Callback {
+                                           callback: callback,
+                                           setup: self.setup,
+                                       });
+
+        struct Callback<CB> {
+            callback: CB,
+            setup: Setup,
+        }
+
+        impl<T, CB> ProducerCallback<T> for Callback<CB>
+            where CB: ProducerCallback<T>
+        {
+            type Output = CB::Output;
+
+            fn callback<P>(self, base: P) -> CB::Output
+                where P: Producer<Item = T>
+            {
+                let producer = SetupProducer {
+                    base: base,
+                    setup: self.setup,
+                };
+                self.callback.callback(producer)
+            }
+        }
+    }
+}
+
+struct SetupProducer<P> {
+    base: P,
+    setup: Setup,
+}
+
+impl<P> Producer for SetupProducer<P>
+    where P: Producer
+{
+    type Item = P::Item;
+    type IntoIter = P::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.base.into_iter()
+    }
+
+    fn min_len(&self) -> usize {
+        self.setup.min_len(self.base.min_len())
+    }
+    fn max_len(&self) -> usize {
+        self.setup.max_len(self.base.max_len())
+    }
+
+    fn split_at(self, index: usize) -> (Self, Self) {
+        let (left, right) = self.base.split_at(index);
+        (SetupProducer {
+             base: left,
+             setup: self.setup,
+         },
+         SetupProducer {
+             base: right,
+             setup: self.setup,
+         })
+    }
+
+    fn fold_with<G>(self, folder: G) -> G
+        where G: Folder<Self::Item>
+    {
+        self.base.fold_with(folder)
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+/// Strategy for running a consumer-driving computation.
+///
+/// An `Executor` is handed a closure that performs the `bridge`/`join`
+/// recursion and decides how it runs: synchronously on the current worker, or
+/// handed off to an alternative scheduler. `D` is the consumer `Result` the
+/// closure yields; whatever the executor, splitting and reduction happen in
+/// exactly the same order, so the result is identical.
+///
+/// The `drive`/`drive_unindexed` methods of the [`ParallelIterator`] adaptors
+/// do not take an `Executor`: they run the recursion synchronously and return
+/// the consumer result directly. An executor is used by passing such a closure
+/// to `exec`.
+///
+/// [`ParallelIterator`]: trait.ParallelIterator.html
+pub trait Executor<D>
+    where D: Send + 'static
+{
+    /// The value produced once the computation has been scheduled: `D` itself
+    /// for a synchronous executor, or a handle/future resolving to `D` for an
+    /// asynchronous one.
+    type Result;
+
+    /// Run `split`, which performs the join-based recursion and returns the
+    /// reduced consumer result `D`.
+    ///
+    /// The `'static` bound lets an asynchronous executor outlive the call by
+    /// moving `split` onto the pool; a synchronous executor simply runs it.
+    fn exec<OP>(self, split: OP) -> Self::Result
+        where OP: FnOnce() -> D + Send + 'static;
+}
+
+/// The default executor: reproduces today's behavior by running the
+/// `bridge`/`join` recursion inline and returning the consumer result directly.
+///
+/// Also known as the `Sequential` executor.
+pub struct JoinExecutor;
+
+/// Alias for the default, synchronous [`JoinExecutor`].
+///
+/// [`JoinExecutor`]: struct.JoinExecutor.html
+pub type Sequential = JoinExecutor;
+
+impl<D> Executor<D> for JoinExecutor
+    where D: Send + 'static
+{
+    type Result = D;
+
+    fn exec<OP>(self, split: OP) -> D
+        where OP: FnOnce() -> D + Send + 'static
+    {
+        split()
+    }
+}
+
+/// An executor that spawns the top-level split onto the thread pool via
+/// `spawn_future` and returns a `RayonFuture` resolving to the consumer result,
+/// instead of blocking a pool worker until the computation completes.
+#[cfg(rayon_unstable)]
+pub struct FutureExecutor;
+
+#[cfg(rayon_unstable)]
+impl<D> Executor<D> for FutureExecutor
+    where D: Send + 'static
+{
+    type Result = ::RayonFuture<D>;
+
+    fn exec<OP>(self, split: OP) -> ::RayonFuture<D>
+        where OP: FnOnce() -> D + Send + 'static
+    {
+        ::spawn_future(::futures::future::lazy(move || Ok(split())))
+    }
+}
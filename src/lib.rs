@@ -10,6 +10,9 @@
 extern crate rayon_core;
 extern crate either;
 
+#[cfg(rayon_unstable)]
+extern crate futures;
+
 #[cfg(test)]
 extern crate rand;
 